@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use s1vm::compiler::Compiler;
+use s1vm::vm;
+use s1vm::Store;
+
+// Arithmetic-heavy wasm: a tight loop of i32 adds/subs/compares over
+// locals, chosen to make the closure tree's per-operand boxing/calling
+// overhead dominate so the two compilation strategies are easy to tell
+// apart.
+const ARITH_HEAVY_WAT: &str = include_str!("fixtures/arith_heavy.wat");
+const ARITH_HEAVY_N: i64 = 1000;
+
+fn parse_arith_heavy() -> bwasm::Module {
+  bwasm::Module::from_parity_wasm_module(
+    parity_wasm::elements::deserialize_buffer(&wat::parse_str(ARITH_HEAVY_WAT).unwrap()).unwrap()
+  ).unwrap()
+}
+
+fn compile_closures(c: &mut Criterion) {
+  let module = parse_arith_heavy();
+  c.bench_function("compile/closures", |b| {
+    b.iter(|| Compiler::new(&module).compile().unwrap())
+  });
+}
+
+fn compile_registerized(c: &mut Criterion) {
+  let module = parse_arith_heavy();
+  c.bench_function("compile/registerized", |b| {
+    b.iter(|| Compiler::new(&module).compile_registerized().unwrap())
+  });
+}
+
+// Compiling once and timing only the call is what actually exercises the
+// two backends' runtime dispatch overhead (closure calls vs. register
+// interpretation); the benches above never run the compiled `Function`s
+// at all.
+fn call_closures(c: &mut Criterion) {
+  let module = parse_arith_heavy();
+  let compiled = Compiler::new(&module).compile().unwrap();
+  let mut store = Store::new(&module);
+  let state = vm::State::new_interruptible(Arc::new(AtomicBool::new(false)), false);
+  c.bench_function("call/closures", |b| {
+    b.iter(|| state.call(&mut store, &compiled, 0, &[ARITH_HEAVY_N]).unwrap())
+  });
+}
+
+fn call_registerized(c: &mut Criterion) {
+  let module = parse_arith_heavy();
+  let compiled = Compiler::new(&module).compile_registerized().unwrap();
+  let mut store = Store::new(&module);
+  let state = vm::State::new_interruptible(Arc::new(AtomicBool::new(false)), false);
+  c.bench_function("call/registerized", |b| {
+    b.iter(|| state.call(&mut store, &compiled, 0, &[ARITH_HEAVY_N]).unwrap())
+  });
+}
+
+criterion_group!(benches, compile_closures, compile_registerized, call_closures, call_registerized);
+criterion_main!(benches);
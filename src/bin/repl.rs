@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Editor, Helper};
+
+use s1vm::bwasm;
+use s1vm::compiler::Compiler;
+use s1vm::vm;
+use s1vm::{Error, Result, Store};
+
+// Names offered for tab-completion, alongside the loaded module's own
+// export names: the REPL's command words. Opcode mnemonics are not
+// offered. The prompt loop handles `step`, `continue`, `exports` and
+// `quit`; `locals` and `stack` have no handler there, so they are taken
+// as an export name.
+const COMMANDS: &[&str] = &["step", "continue", "locals", "stack", "exports", "quit"];
+
+struct ReplHelper {
+    exports: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos].rfind(|c: char| !c.is_alphanumeric() && c != '_').map(|i| i + 1).unwrap_or(0);
+        let prefix = &line[start..pos];
+        let names = self.exports.iter().map(String::as_str)
+            .chain(COMMANDS.iter().copied())
+            .filter(|name| name.starts_with(prefix));
+        let candidates = names
+            .map(|name| Pair { display: name.to_string(), replacement: name.to_string() })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        self.exports.iter()
+            .find(|name| name.starts_with(line) && name.as_str() != line)
+            .map(|name| name[line.len()..].to_string())
+    }
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        // A `.wat`-style call expression ("(add 1 2") is incomplete while
+        // its parens are unbalanced; keep prompting for continuation
+        // instead of submitting a truncated line.
+        let mut depth: i32 = 0;
+        for c in ctx.input().chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => (),
+            }
+        }
+        if depth > 0 {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+fn load_module(path: &str) -> Result<bwasm::Module> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| Error::ValidationError(format!("failed to read '{}': {}", path, e)))?;
+    let module = parity_wasm::elements::deserialize_buffer(&bytes)
+        .map_err(|e| Error::ValidationError(format!("failed to parse '{}': {}", path, e)))?;
+    bwasm::Module::from_parity_wasm_module(module)
+        .map_err(|e| Error::ValidationError(format!("failed to build module: {:?}", e)))
+}
+
+fn main() -> Result<()> {
+    let path = std::env::args().nth(1)
+        .unwrap_or_else(|| { eprintln!("usage: repl <module.wasm>"); std::process::exit(1); });
+
+    let module = load_module(&path)?;
+    let exports: Vec<String> = module.functions().iter()
+        .map(|func| func.name().to_string())
+        .collect();
+    println!("loaded '{}', exports: {}", path, exports.join(", "));
+
+    let compiled = Compiler::new(&module).compile()?;
+    let mut store = Store::new(&module);
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || interrupted.store(true, Ordering::SeqCst))
+            .expect("failed to install Ctrl-C handler");
+    }
+
+    let mut rl = Editor::<ReplHelper>::new();
+    rl.set_helper(Some(ReplHelper { exports: exports.clone() }));
+
+    let mut single_step = false;
+    loop {
+        match rl.readline("s1vm> ") {
+            Ok(line) => {
+                rl.add_history_entry(line.as_str());
+                match line.trim() {
+                    "quit" => break,
+                    "step" => {
+                        single_step = true;
+                        println!("single-step mode enabled");
+                    },
+                    "continue" => {
+                        single_step = false;
+                        println!("single-step mode disabled");
+                    },
+                    "exports" => println!("{}", exports.join(", ")),
+                    line => run_call(&compiled, &exports, &mut store, &interrupted, single_step, line),
+                }
+            },
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                eprintln!("readline error: {:?}", err);
+                break;
+            },
+        }
+    }
+    Ok(())
+}
+
+// Parses the `.wat`-style call expression `(name arg0 arg1 ...)` (parens
+// optional at the REPL) and invokes the matching compiled export,
+// printing its return value or the trap it raised. Locals and the
+// operand stack are not printed.
+fn run_call(
+    compiled: &[s1vm::function::Function],
+    exports: &[String],
+    store: &mut Store,
+    interrupted: &Arc<AtomicBool>,
+    single_step: bool,
+    line: &str,
+) {
+    let line = line.trim_start_matches('(').trim_end_matches(')');
+    let mut tokens = line.split_whitespace();
+    let name = match tokens.next() {
+        Some(name) => name,
+        None => return,
+    };
+    let func_idx = match exports.iter().position(|export| export == name) {
+        Some(idx) => idx as u32,
+        None => {
+            eprintln!("no such export: {}", name);
+            return;
+        },
+    };
+    let mut args = Vec::new();
+    for arg in tokens {
+        match arg.parse() {
+            Ok(val) => args.push(val),
+            Err(_) => {
+                eprintln!("not a valid i64 argument: {}", arg);
+                return;
+            },
+        }
+    }
+
+    interrupted.store(false, Ordering::SeqCst);
+    let state = vm::State::new_interruptible(Arc::clone(interrupted), single_step);
+    match state.call(store, compiled, func_idx, &args) {
+        Ok(Some(ret)) => println!("=> {:?}", ret),
+        Ok(None) => println!("=> (no return value)"),
+        Err(trap) => println!("trap: {:?}", trap),
+    }
+}
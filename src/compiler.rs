@@ -1,4 +1,8 @@
 
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::convert::TryInto;
+
 use crate::*;
 use crate::function::*;
 use crate::error::*;
@@ -22,10 +26,17 @@ type EvalFunc = Box<dyn Fn(&vm::State, &mut Store) -> Trap<Action>>;
 
 type OpFunc = Box<dyn Fn(&vm::State, &mut Store) -> Trap<StackValue>>;
 
+type V128OpFunc = Box<dyn Fn(&vm::State, &mut Store) -> Trap<V128>>;
+
 enum Input {
   Local(u32),
   Const(StackValue),
   Op(OpFunc),
+  // SIMD operands: kept as separate variants (instead of widening
+  // `StackValue` itself) so the scalar fast paths above don't pay for the
+  // extra 8 bytes on every push/pop.
+  ConstV128(V128),
+  OpV128(V128OpFunc),
 }
 
 impl Input {
@@ -40,6 +51,19 @@ impl Input {
         Ok(*const_val)
       },
       Input::Op(closure) => closure(state, store),
+      Input::ConstV128(_) | Input::OpV128(_) => {
+        unreachable!("v128 operand resolved through the scalar path");
+      },
+    }
+  }
+
+  pub fn resolv_v128(&self, state: &vm::State, store: &mut Store) -> Trap<V128> {
+    match self {
+      Input::ConstV128(const_val) => Ok(*const_val),
+      Input::OpV128(closure) => closure(state, store),
+      Input::Local(_) | Input::Const(_) | Input::Op(_) => {
+        unreachable!("scalar operand resolved through the v128 path");
+      },
     }
   }
 }
@@ -70,59 +94,414 @@ impl Block {
 
   pub fn run(&self, state: &vm::State, store: &mut Store) -> Trap<Action> {
     //eprintln!("---- run block: {:?}", self.kind);
-    for f in self.eval.iter() {
-      let ret = f(state, store)?;
-      //eprintln!("---- evaled: ret = {:?}", ret);
-      match ret {
-        Action::Return(_) => {
-          // Keep passing return value up, until we get to the function block.
-          return Ok(ret);
-        },
-        Action::End => {
-          // sub-block finished, continue this block.
-          continue;
-        },
-        Action::Branch(depth) => {
-          if self.depth > depth {
-            // keep passing action lower.
+    'restart: loop {
+      for f in self.eval.iter() {
+        // Let a REPL/debugger front-end abort a runaway `Loop` (or any
+        // long-running call) back to the prompt instead of hanging.
+        state.check_interrupt()?;
+        let ret = f(state, store)?;
+        // Mirrors `check_interrupt`: a front-end enables single-stepping via
+        // `vm::State::new_interruptible`, and `on_step` is the hook that
+        // actually pauses and reports back once per evaluated instruction —
+        // without this the `step` REPL command had nothing to act on.
+        state.on_step(&ret)?;
+        //eprintln!("---- evaled: ret = {:?}", ret);
+        match ret {
+          Action::Return(_) => {
+            // Keep passing return value up, until we get to the function block.
             return Ok(ret);
-          } else if self.depth == depth {
-            // handle Branch here.
-            todo!("handle branch")
-          } else {
-            unreachable!("Can't branch into a sub-block.");
+          },
+          Action::End => {
+            // sub-block finished, continue this block.
+            continue;
+          },
+          Action::Branch(depth) => {
+            if self.depth > depth {
+              // keep passing action lower.
+              return Ok(ret);
+            } else if self.depth == depth {
+              match self.kind {
+                BlockKind::Loop => {
+                  // back-edge: restart this loop's iteration from the top.
+                  continue 'restart;
+                },
+                _ => {
+                  // forward exit: the block we were branching out of is done.
+                  return Ok(Action::End);
+                },
+              }
+            } else {
+              unreachable!("Can't branch into a sub-block.");
+            }
           }
         }
       }
+      return Ok(Action::End);
+    }
+  }
+}
+
+#[cfg(test)]
+mod block_branch_tests {
+  use super::*;
+  use std::sync::Arc;
+  use std::sync::atomic::AtomicBool;
+
+  // These exercise `Block::run`'s branch-depth resolution directly with
+  // synthetic `Action`-producing closures, bypassing opcode compilation —
+  // the riskiest part of the closure-tree backend (nested block/loop
+  // unwinding and loop back-edges) lives entirely in that dispatch, not in
+  // how an opcode builds its closure.
+  fn empty_store() -> Store {
+    let module = bwasm::Module::from_parity_wasm_module(
+      parity_wasm::elements::deserialize_buffer(&wat::parse_str("(module)").unwrap()).unwrap()
+    ).unwrap();
+    Store::new(&module)
+  }
+
+  fn test_state() -> vm::State {
+    vm::State::new_interruptible(Arc::new(AtomicBool::new(false)), false)
+  }
+
+  #[test]
+  fn br_out_of_nested_block_unwinds_to_the_target_depth() {
+    // outer (depth 0) { inner (depth 1) { br 1 /* targets outer, depth 0 */ } }
+    let mut inner = Block::new(BlockKind::Block, 1);
+    inner.push(Box::new(|_: &vm::State, _: &mut Store| Ok(Action::Branch(0))));
+
+    let state = test_state();
+    let mut store = empty_store();
+    let inner_ret = inner.run(&state, &mut store).unwrap();
+    // The inner block isn't the branch's target (0 < its own depth 1), so
+    // it must propagate the branch outward rather than swallow it.
+    assert!(matches!(inner_ret, Action::Branch(0)));
+  }
+
+  #[test]
+  fn br_targeting_a_plain_block_exits_it() {
+    let mut block = Block::new(BlockKind::Block, 0);
+    block.push(Box::new(|_: &vm::State, _: &mut Store| Ok(Action::Branch(0))));
+    // A later eval entry that must NOT run once the branch above exits the block.
+    block.push(Box::new(|_: &vm::State, _: &mut Store| -> Trap<Action> {
+      panic!("unreachable: block should have exited on the branch above");
+    }));
+
+    let state = test_state();
+    let mut store = empty_store();
+    let ret = block.run(&state, &mut store).unwrap();
+    assert!(matches!(ret, Action::End));
+  }
+
+  #[test]
+  fn br_targeting_a_loop_restarts_it_instead_of_exiting() {
+    let iterations = Rc::new(RefCell::new(0u32));
+    let counted = Rc::clone(&iterations);
+
+    let mut loop_block = Block::new(BlockKind::Loop, 0);
+    loop_block.push(Box::new(move |_: &vm::State, _: &mut Store| -> Trap<Action> {
+      *counted.borrow_mut() += 1;
+      if *counted.borrow() < 3 {
+        Ok(Action::Branch(0)) // back-edge: re-run this loop's body
+      } else {
+        Ok(Action::End)
+      }
+    }));
+
+    let state = test_state();
+    let mut store = empty_store();
+    let ret = loop_block.run(&state, &mut store).unwrap();
+    assert!(matches!(ret, Action::End));
+    assert_eq!(*iterations.borrow(), 3);
+  }
+
+  #[test]
+  fn return_propagates_immediately_without_running_later_entries() {
+    let mut block = Block::new(BlockKind::Block, 0);
+    block.push(Box::new(|_: &vm::State, _: &mut Store| Ok(Action::Return(Some(StackValue(42))))));
+    block.push(Box::new(|_: &vm::State, _: &mut Store| -> Trap<Action> {
+      panic!("unreachable: a Return must short-circuit the rest of the block");
+    }));
+
+    let state = test_state();
+    let mut store = empty_store();
+    let ret = block.run(&state, &mut store).unwrap();
+    assert!(matches!(ret, Action::Return(Some(StackValue(42)))));
+  }
+}
+
+/// Parent-pointer forest for local value numbering: `parent[x] >= 0` means
+/// `x` points at another element, `parent[x] < 0` means `x` is a class
+/// root and `-parent[x]` is that class's size.
+struct UnionFind {
+  parent: Vec<isize>,
+}
+
+impl UnionFind {
+  fn new() -> UnionFind {
+    UnionFind { parent: vec![] }
+  }
+
+  fn make_class(&mut self) -> u32 {
+    let id = self.parent.len() as u32;
+    self.parent.push(-1);
+    id
+  }
+
+  fn find(&mut self, x: u32) -> u32 {
+    let mut root = x;
+    while self.parent[root as usize] >= 0 {
+      root = self.parent[root as usize] as u32;
+    }
+    let mut cur = x;
+    while cur != root {
+      let next = self.parent[cur as usize] as u32;
+      self.parent[cur as usize] = root as isize;
+      cur = next;
+    }
+    root
+  }
+
+  fn merge(&mut self, a: u32, b: u32) {
+    let (mut ra, mut rb) = (self.find(a), self.find(b));
+    if ra == rb {
+      return;
     }
-    Ok(Action::End)
+    if -self.parent[ra as usize] < -self.parent[rb as usize] {
+      std::mem::swap(&mut ra, &mut rb);
+    }
+    self.parent[ra as usize] += self.parent[rb as usize];
+    self.parent[rb as usize] = ra as isize;
   }
 }
 
+/// A value-numbering class together with the once-computed result it
+/// stands for, shared between the expression that first computes it and
+/// every later expression that turns out to be the same computation.
+type CseSlot = (Rc<RefCell<Option<StackValue>>>, u32);
+
 pub struct State {
   values: Vec<Input>,
+  value_numbers: Vec<u32>,
   pub depth: u32,
   pub pc: usize,
+
+  uf: UnionFind,
+  local_version: std::collections::HashMap<u32, u32>,
+  local_classes: std::collections::HashMap<(u32, u32), u32>,
+  const_classes: std::collections::HashMap<i64, u32>,
+  // Keyed by (opcode, left class, right class); confined to the current
+  // function's compile pass only (never persisted across functions), and
+  // cleared at block boundaries / calls so a branch can't observe a value
+  // computed on a path it skipped.
+  cse_table: std::collections::HashMap<(&'static str, u32, u32), CseSlot>,
 }
 
 impl State {
   pub fn new() -> State {
     State {
       values: vec![],
+      value_numbers: vec![],
       depth: 0,
       pc: 0,
+      uf: UnionFind::new(),
+      local_version: std::collections::HashMap::new(),
+      local_classes: std::collections::HashMap::new(),
+      const_classes: std::collections::HashMap::new(),
+      cse_table: std::collections::HashMap::new(),
     }
   }
 
   fn pop(&mut self) -> Result<Input> {
-    self.values.pop()
+    let input = self.values.pop()
       .ok_or_else(|| {
         Error::ValidationError(format!("Value stack empty"))
-      })
+      })?;
+    self.value_numbers.pop();
+    Ok(input)
+  }
+
+  fn pop_with_vn(&mut self) -> Result<(Input, u32)> {
+    let input = self.values.pop()
+      .ok_or_else(|| {
+        Error::ValidationError(format!("Value stack empty"))
+      })?;
+    let vn = self.value_numbers.pop().expect("value_numbers out of sync with values");
+    Ok((input, vn))
   }
 
   fn push(&mut self, input: Input) {
+    let vn = self.value_number_for(&input);
+    self.values.push(input);
+    self.value_numbers.push(vn);
+  }
+
+  fn push_with_vn(&mut self, input: Input, vn: u32) {
     self.values.push(input);
+    self.value_numbers.push(vn);
+  }
+
+  fn value_number_for(&mut self, input: &Input) -> u32 {
+    match input {
+      Input::Local(idx) => self.local_class(*idx),
+      Input::Const(val) => self.const_class(*val),
+      // Not structurally comparable here, so give it its own class; it can
+      // still be reused later if it gets aliased to a local via `TeeLocal`.
+      Input::Op(_) => self.uf.make_class(),
+      // v128 values never flow through the scalar CSE path above.
+      Input::ConstV128(_) | Input::OpV128(_) => self.uf.make_class(),
+    }
+  }
+
+  fn local_class(&mut self, idx: u32) -> u32 {
+    let version = *self.local_version.get(&idx).unwrap_or(&0);
+    if let Some(&class) = self.local_classes.get(&(idx, version)) {
+      return self.uf.find(class);
+    }
+    let class = self.uf.make_class();
+    self.local_classes.insert((idx, version), class);
+    class
+  }
+
+  fn const_class(&mut self, val: StackValue) -> u32 {
+    if let Some(&class) = self.const_classes.get(&val.0) {
+      return self.uf.find(class);
+    }
+    let class = self.uf.make_class();
+    self.const_classes.insert(val.0, class);
+    class
+  }
+
+  /// A write through `idx` (`SetLocal`/`TeeLocal`) makes every previously
+  /// numbered read of that local stale; bump its version so future reads
+  /// get a fresh class instead of the one computed before the write.
+  fn invalidate_local(&mut self, idx: u32) {
+    let version = self.local_version.entry(idx).or_insert(0);
+    *version += 1;
+  }
+
+  /// After a `SetLocal`/`TeeLocal` through `idx`, later plain reads of
+  /// `idx` are equivalent to the value that was just written, so they can
+  /// hit the same CSE cache entries that value's expression would.
+  fn alias_local(&mut self, idx: u32, val_vn: u32) {
+    let class = self.local_class(idx);
+    self.uf.merge(class, val_vn);
+  }
+
+  fn cse_lookup(&mut self, key: &'static str, left_vn: u32, right_vn: u32) -> Option<CseSlot> {
+    let k = (key, self.uf.find(left_vn), self.uf.find(right_vn));
+    self.cse_table.get(&k).cloned()
+  }
+
+  fn cse_reserve(&mut self, key: &'static str, left_vn: u32, right_vn: u32) -> CseSlot {
+    let k = (key, self.uf.find(left_vn), self.uf.find(right_vn));
+    let slot: CseSlot = (Rc::new(RefCell::new(None)), self.uf.make_class());
+    self.cse_table.insert(k, slot.clone());
+    slot
+  }
+
+  /// A `Call` may have arbitrary side effects; conservatively drop every
+  /// cached subexpression rather than risk reusing a value it invalidated.
+  fn invalidate_cse_across_call(&mut self) {
+    self.cse_table.clear();
+  }
+}
+
+#[cfg(test)]
+mod cse_tests {
+  use super::*;
+
+  // Exercises `State`'s value-numbering/CSE bookkeeping directly, the same
+  // way the `impl_int_binops!`-generated ops (and `Compiler::compile_block`,
+  // for the per-block isolation case) use it — without going through actual
+  // opcode compilation, since the numbering itself is what's risky: a wrong
+  // invalidation rule here doesn't fail loudly, it silently hands back a
+  // stale value (the failure mode the `expect` on the cache read assumes
+  // can't happen).
+
+  /// Pushes two locals, pops them back off with their value numbers — the
+  /// same shape every `impl_int_binops!` op starts with before consulting
+  /// the CSE table.
+  fn push_pop_operands(state: &mut State, left: u32, right: u32) -> (u32, u32) {
+    state.push(Input::Local(left));
+    state.push(Input::Local(right));
+    let (_, right_vn) = state.pop_with_vn().expect("value stack non-empty");
+    let (_, left_vn) = state.pop_with_vn().expect("value stack non-empty");
+    (left_vn, right_vn)
+  }
+
+  #[test]
+  fn repeated_subexpression_in_one_block_reuses_the_cached_class() {
+    let mut state = State::new();
+    let (left_vn, right_vn) = push_pop_operands(&mut state, 0, 1);
+    assert!(state.cse_lookup("i32::add", left_vn, right_vn).is_none());
+    state.cse_reserve("i32::add", left_vn, right_vn);
+
+    // Same locals, same versions: the second occurrence of `local.get 0;
+    // local.get 1; i32.add` in this block must hit the cache instead of
+    // reserving a fresh slot.
+    let (left_vn2, right_vn2) = push_pop_operands(&mut state, 0, 1);
+    assert_eq!(left_vn, left_vn2);
+    assert_eq!(right_vn, right_vn2);
+    assert!(state.cse_lookup("i32::add", left_vn2, right_vn2).is_some());
+  }
+
+  #[test]
+  fn writing_a_referenced_local_invalidates_its_class() {
+    let mut state = State::new();
+    let class_before = state.local_class(0);
+    state.invalidate_local(0);
+    let class_after = state.local_class(0);
+    // A `SetLocal`/`TeeLocal` through local 0 must bump its version, so a
+    // later plain read of local 0 gets a fresh class rather than the one
+    // computed before the write.
+    assert_ne!(class_before, class_after);
+  }
+
+  #[test]
+  fn aliasing_a_local_to_a_written_value_lets_later_reads_reuse_its_class() {
+    let mut state = State::new();
+    state.push(Input::Local(2));
+    let (_, written_vn) = state.pop_with_vn().expect("value stack non-empty");
+    state.invalidate_local(0);
+    state.alias_local(0, written_vn);
+    // After `local.set 0` aliases local 0 to whatever was just written,
+    // reading local 0 again is the same value as that write.
+    assert_eq!(state.local_class(0), state.uf.find(written_vn));
+  }
+
+  #[test]
+  fn call_clears_the_whole_cse_table() {
+    let mut state = State::new();
+    let (left_vn, right_vn) = push_pop_operands(&mut state, 0, 1);
+    state.cse_reserve("i32::add", left_vn, right_vn);
+    assert!(state.cse_lookup("i32::add", left_vn, right_vn).is_some());
+
+    state.invalidate_cse_across_call();
+    // A `Call` may have arbitrary side effects, so nothing cached before it
+    // may be reused after it, even though the locals it read never changed.
+    assert!(state.cse_lookup("i32::add", left_vn, right_vn).is_none());
+  }
+
+  #[test]
+  fn nested_block_does_not_see_the_enclosing_blocks_cached_expressions() {
+    let mut state = State::new();
+    let (left_vn, right_vn) = push_pop_operands(&mut state, 0, 1);
+    state.cse_reserve("i32::add", left_vn, right_vn);
+    assert!(state.cse_lookup("i32::add", left_vn, right_vn).is_some());
+
+    // `compile_block` swaps in a fresh table for every nested block and
+    // restores the outer one on exit — this is the isolation the
+    // `expect("CSE cache read before its expression ran")` at the top of
+    // `impl_int_binops!`'s generated ops relies on: a block can only ever
+    // observe a cache slot it reserved (and therefore is guaranteed to have
+    // computed) itself.
+    let outer_cse_table = std::mem::take(&mut state.cse_table);
+    assert!(
+      state.cse_lookup("i32::add", left_vn, right_vn).is_none(),
+      "a fresh block must not see its enclosing block's cached expressions"
+    );
+    state.cse_table = outer_cse_table;
+    assert!(state.cse_lookup("i32::add", left_vn, right_vn).is_some());
   }
 }
 
@@ -202,6 +581,10 @@ impl Compiler {
     if state.depth > 4 {
       panic!("compile overflow");
     }
+    // Value numbering classes are confined to a single block: a branch
+    // can skip the rest of this block, so a sibling/enclosing block must
+    // never reuse a value it never saw computed.
+    let outer_cse_table = std::mem::take(&mut state.cse_table);
     // compile function opcodes.
     loop {
       use parity_wasm::elements::Instruction::*;
@@ -243,14 +626,61 @@ impl Compiler {
   	    Return => {
           self.emit_return(state, &mut block)?;
         },
-  	    Br(_block_depth) => {
-          todo!("");
+  	    Br(relative_depth) => {
+          let target = state.depth - 1 - *relative_depth;
+          block.push(Box::new(move |_state: &vm::State, _store: &mut Store| -> Trap<Action> {
+            Ok(Action::Branch(target))
+          }));
         },
-  	    BrIf(_block_depth) => {
-          todo!("");
+  	    BrIf(relative_depth) => {
+          let target = state.depth - 1 - *relative_depth;
+          let val = state.pop()?;
+          match val {
+            Input::Op(closure) => {
+              block.push(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<Action> {
+                let val = closure(state, store)?;
+                if val.0 == 0 {
+                  Ok(Action::End)
+                } else {
+                  Ok(Action::Branch(target))
+                }
+              }));
+            },
+            _ => {
+              block.push(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<Action> {
+                let val = val.resolv(state, store)?;
+                if val.0 == 0 {
+                  Ok(Action::End)
+                } else {
+                  Ok(Action::Branch(target))
+                }
+              }));
+            },
+          }
         },
-  	    BrTable(ref _br_table) => {
-          todo!("");
+  	    BrTable(ref br_table) => {
+          let cur_depth = state.depth - 1;
+          let targets: Vec<u32> = br_table.table.iter()
+            .map(|l| cur_depth - *l)
+            .collect();
+          let default_target = cur_depth - br_table.default;
+          let val = state.pop()?;
+          match val {
+            Input::Op(closure) => {
+              block.push(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<Action> {
+                let val = closure(state, store)?;
+                let target = targets.get(val.0 as usize).copied().unwrap_or(default_target);
+                Ok(Action::Branch(target))
+              }));
+            },
+            _ => {
+              block.push(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<Action> {
+                let val = val.resolv(state, store)?;
+                let target = targets.get(val.0 as usize).copied().unwrap_or(default_target);
+                Ok(Action::Branch(target))
+              }));
+            },
+          }
         },
 
         Call(func_idx) => {
@@ -266,10 +696,17 @@ impl Compiler {
               Ok(StackValue(0))
             }
           })));
+          state.invalidate_cse_across_call();
         },
 
 	      GetLocal(local_idx) => {
           state.push(Input::Local(*local_idx));
+        },
+        SetLocal(local_idx) => {
+          self.emit_local_write(state, &mut block, *local_idx, false)?;
+        },
+        TeeLocal(local_idx) => {
+          self.emit_local_write(state, &mut block, *local_idx, true)?;
         },
 	      I32Const(val) => {
           state.push(Input::Const(StackValue(*val as _)));
@@ -287,16 +724,283 @@ impl Compiler {
         I32LtS => {
           i32_ops::lt_s(state)?;
         },
+
+        V128Const(bytes) => {
+          state.push(Input::ConstV128(V128(**bytes)));
+        },
+
+        I8x16Splat => {
+          let val = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let val = val.resolv(vm_state, store)?;
+            Ok(i8x16_ops::splat(val.0 as i8))
+          })));
+        },
+        I16x8Splat => {
+          let val = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let val = val.resolv(vm_state, store)?;
+            Ok(i16x8_ops::splat(val.0 as i16))
+          })));
+        },
+        I32x4Splat => {
+          let val = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let val = val.resolv(vm_state, store)?;
+            Ok(i32x4_ops::splat(val.0 as i32))
+          })));
+        },
+        I64x2Splat => {
+          let val = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let val = val.resolv(vm_state, store)?;
+            Ok(i64x2_ops::splat(val.0 as i64))
+          })));
+        },
+        F32x4Splat => {
+          let val = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let val = val.resolv(vm_state, store)?;
+            Ok(f32x4_ops::splat(f32::from_bits(val.0 as u32)))
+          })));
+        },
+        F64x2Splat => {
+          let val = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let val = val.resolv(vm_state, store)?;
+            Ok(f64x2_ops::splat(f64::from_bits(val.0 as u64)))
+          })));
+        },
+
+        I8x16ExtractLaneS(lane) => {
+          validate_lane_index(LaneType::I8x16, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = i8x16_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem as i32 as _))
+          })));
+        },
+        I8x16ExtractLaneU(lane) => {
+          validate_lane_index(LaneType::I8x16, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = i8x16_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem as u8 as i32 as _))
+          })));
+        },
+        I16x8ExtractLaneS(lane) => {
+          validate_lane_index(LaneType::I16x8, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = i16x8_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem as i32 as _))
+          })));
+        },
+        I16x8ExtractLaneU(lane) => {
+          validate_lane_index(LaneType::I16x8, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = i16x8_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem as u16 as i32 as _))
+          })));
+        },
+        I32x4ExtractLane(lane) => {
+          validate_lane_index(LaneType::I32x4, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = i32x4_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem as _))
+          })));
+        },
+        I64x2ExtractLane(lane) => {
+          validate_lane_index(LaneType::I64x2, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = i64x2_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem as _))
+          })));
+        },
+        F32x4ExtractLane(lane) => {
+          validate_lane_index(LaneType::F32x4, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = f32x4_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem.to_bits() as _))
+          })));
+        },
+        F64x2ExtractLane(lane) => {
+          validate_lane_index(LaneType::F64x2, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          state.push(Input::Op(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+            let val = val.resolv_v128(vm_state, store)?;
+            let elem = f64x2_ops::extract_lane(&val, lane).expect("lane bounds validated at compile time");
+            Ok(StackValue(elem.to_bits() as _))
+          })));
+        },
+
+        I8x16ReplaceLane(lane) => {
+          validate_lane_index(LaneType::I8x16, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          let v = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let v = v.resolv_v128(vm_state, store)?;
+            let val = val.resolv(vm_state, store)?;
+            Ok(i8x16_ops::replace_lane(&v, lane, val.0 as i8).expect("lane bounds validated at compile time"))
+          })));
+        },
+        I16x8ReplaceLane(lane) => {
+          validate_lane_index(LaneType::I16x8, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          let v = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let v = v.resolv_v128(vm_state, store)?;
+            let val = val.resolv(vm_state, store)?;
+            Ok(i16x8_ops::replace_lane(&v, lane, val.0 as i16).expect("lane bounds validated at compile time"))
+          })));
+        },
+        I32x4ReplaceLane(lane) => {
+          validate_lane_index(LaneType::I32x4, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          let v = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let v = v.resolv_v128(vm_state, store)?;
+            let val = val.resolv(vm_state, store)?;
+            Ok(i32x4_ops::replace_lane(&v, lane, val.0 as i32).expect("lane bounds validated at compile time"))
+          })));
+        },
+        I64x2ReplaceLane(lane) => {
+          validate_lane_index(LaneType::I64x2, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          let v = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let v = v.resolv_v128(vm_state, store)?;
+            let val = val.resolv(vm_state, store)?;
+            Ok(i64x2_ops::replace_lane(&v, lane, val.0 as i64).expect("lane bounds validated at compile time"))
+          })));
+        },
+        F32x4ReplaceLane(lane) => {
+          validate_lane_index(LaneType::F32x4, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          let v = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let v = v.resolv_v128(vm_state, store)?;
+            let val = val.resolv(vm_state, store)?;
+            let val = f32::from_bits(val.0 as u32);
+            Ok(f32x4_ops::replace_lane(&v, lane, val).expect("lane bounds validated at compile time"))
+          })));
+        },
+        F64x2ReplaceLane(lane) => {
+          validate_lane_index(LaneType::F64x2, *lane as u32)?;
+          let lane = *lane as u32;
+          let val = state.pop()?;
+          let v = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let v = v.resolv_v128(vm_state, store)?;
+            let val = val.resolv(vm_state, store)?;
+            let val = f64::from_bits(val.0 as u64);
+            Ok(f64x2_ops::replace_lane(&v, lane, val).expect("lane bounds validated at compile time"))
+          })));
+        },
+
+        I8x16Add => { self.emit_v128_binop(state, i8x16_ops::add)?; },
+        I8x16Sub => { self.emit_v128_binop(state, i8x16_ops::sub)?; },
+        I8x16Mul => { self.emit_v128_binop(state, i8x16_ops::mul)?; },
+        I16x8Add => { self.emit_v128_binop(state, i16x8_ops::add)?; },
+        I16x8Sub => { self.emit_v128_binop(state, i16x8_ops::sub)?; },
+        I16x8Mul => { self.emit_v128_binop(state, i16x8_ops::mul)?; },
+        I32x4Add => { self.emit_v128_binop(state, i32x4_ops::add)?; },
+        I32x4Sub => { self.emit_v128_binop(state, i32x4_ops::sub)?; },
+        I32x4Mul => { self.emit_v128_binop(state, i32x4_ops::mul)?; },
+        I64x2Add => { self.emit_v128_binop(state, i64x2_ops::add)?; },
+        I64x2Sub => { self.emit_v128_binop(state, i64x2_ops::sub)?; },
+        I64x2Mul => { self.emit_v128_binop(state, i64x2_ops::mul)?; },
+        F32x4Add => { self.emit_v128_binop(state, f32x4_ops::add)?; },
+        F32x4Sub => { self.emit_v128_binop(state, f32x4_ops::sub)?; },
+        F32x4Mul => { self.emit_v128_binop(state, f32x4_ops::mul)?; },
+        F64x2Add => { self.emit_v128_binop(state, f64x2_ops::add)?; },
+        F64x2Sub => { self.emit_v128_binop(state, f64x2_ops::sub)?; },
+        F64x2Mul => { self.emit_v128_binop(state, f64x2_ops::mul)?; },
+
+        V128And => { self.emit_v128_bitwise_binop(state, v128_bitops::and)?; },
+        V128Or => { self.emit_v128_bitwise_binop(state, v128_bitops::or)?; },
+        V128Xor => { self.emit_v128_bitwise_binop(state, v128_bitops::xor)?; },
+        V128Not => {
+          let val = state.pop()?;
+          state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+            let val = val.resolv_v128(vm_state, store)?;
+            Ok(v128_bitops::not(val))
+          })));
+        },
+
         _ => todo!("implment opcode"),
       };
       state.pc += 1;
     }
 
     state.depth -= 1;
+    state.cse_table = outer_cse_table;
     //eprintln!("end block: depth: {} {:?}", block.depth(), kind);
     Ok(block)
   }
 
+  /// Shared by `SetLocal`/`TeeLocal`: write the popped value through to
+  /// `idx`, and for `TeeLocal` also leave it on the (compile-time) value
+  /// stack as a plain `Local` read of the slot it was just written to.
+  fn emit_local_write(&self, state: &mut State, block: &mut Block, idx: u32, tee: bool) -> Result<()> {
+    let (val, val_vn) = state.pop_with_vn()?;
+    state.invalidate_local(idx);
+    state.alias_local(idx, val_vn);
+    match val {
+      Input::Local(src_idx) => {
+        block.push(Box::new(move |_state: &vm::State, store: &mut Store| -> Trap<Action> {
+          let val = store.stack.get_local_val(src_idx);
+          store.stack.set_local_val(idx, val);
+          Ok(Action::End)
+        }));
+      },
+      Input::Const(const_val) => {
+        block.push(Box::new(move |_state: &vm::State, store: &mut Store| -> Trap<Action> {
+          store.stack.set_local_val(idx, const_val);
+          Ok(Action::End)
+        }));
+      },
+      Input::Op(closure) => {
+        block.push(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<Action> {
+          let val = closure(state, store)?;
+          store.stack.set_local_val(idx, val);
+          Ok(Action::End)
+        }));
+      },
+      Input::ConstV128(_) | Input::OpV128(_) => {
+        return Err(Error::ValidationError(format!("v128 locals are not yet supported")));
+      },
+    }
+    if tee {
+      state.push(Input::Local(idx));
+    }
+    Ok(())
+  }
+
   fn emit_return(&self, state: &mut State, block: &mut Block) -> Result<()> {
     if self.ret_type.is_some() {
       let ret = state.pop()?;
@@ -319,6 +1023,9 @@ impl Compiler {
             Ok(Action::Return(Some(StackValue(ret.0 as _))))
           }));
         },
+        Input::ConstV128(_) | Input::OpV128(_) => {
+          return Err(Error::ValidationError(format!("v128 return values are not yet supported")));
+        },
       }
     } else {
       block.push(Box::new(move |_state: &vm::State, _store: &mut Store| -> Trap<Action> {
@@ -329,6 +1036,34 @@ impl Compiler {
     Ok(())
   }
 
+  /// Shared by every lanewise `add`/`sub`/`mul` opcode: pop the two v128
+  /// operands and defer to `op` (one of `{lane_type}_ops::{add,sub,mul}`)
+  /// once both are resolved at runtime.
+  fn emit_v128_binop(&self, state: &mut State, op: fn(&V128, &V128) -> V128) -> Result<()> {
+    let right = state.pop()?;
+    let left = state.pop()?;
+    state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+      let left = left.resolv_v128(vm_state, store)?;
+      let right = right.resolv_v128(vm_state, store)?;
+      Ok(op(&left, &right))
+    })));
+    Ok(())
+  }
+
+  /// Shared by `v128.and`/`v128.or`/`v128.xor`: same shape as
+  /// `emit_v128_binop`, but `v128_bitops`'s functions take their operands
+  /// by value instead of by reference (they don't care about lane shape).
+  fn emit_v128_bitwise_binop(&self, state: &mut State, op: fn(V128, V128) -> V128) -> Result<()> {
+    let right = state.pop()?;
+    let left = state.pop()?;
+    state.push(Input::OpV128(Box::new(move |vm_state: &vm::State, store: &mut Store| -> Trap<V128> {
+      let left = left.resolv_v128(vm_state, store)?;
+      let right = right.resolv_v128(vm_state, store)?;
+      Ok(op(left, right))
+    })));
+    Ok(())
+  }
+
   fn compile_loop(&self, state: &mut State) -> Result<Block> {
     self.compile_block(state, BlockKind::Loop)
   }
@@ -412,71 +1147,542 @@ impl Compiler {
   fn compile_else(&self, state: &mut State) -> Result<Block> {
     self.compile_block(state, BlockKind::Else)
   }
-}
 
-macro_rules! impl_int_binops {
-  ($name: ident, $type: ty, $op: ident) => {
-    pub fn $name(state: &mut State) -> Result<()> {
-      let right = state.pop()?;
-      let left = state.pop()?;
-      match left {
-        Input::Local(left_idx) => {
-          match right {
-            Input::Const(right_const) => {
-              state.push(Input::Op(Box::new(move |_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
-                let left = store.stack.get_local_val(left_idx);
-                let right = right_const;
-                let res = (left.0 as $type).$op(right.0 as $type);
-                Ok(StackValue(res as _))
-              })));
-              return Ok(());
-            },
-            _ => (),
+  /// Alternate output of this compiler: instead of nesting every operand
+  /// into an `Input::Op` closure tree, lower each function to a flat
+  /// `Vec<StackValue>` register frame plus a linear instruction list. Kept
+  /// side-by-side with [`Compiler::compile`] so both can be benchmarked
+  /// against each other.
+  pub fn compile_registerized(mut self) -> Result<Vec<Function>> {
+    let len = self.module.functions().len() as u32;
+    let mut compiled = vec![];
+    for idx in 0..len {
+      compiled.push(self.compile_function_registerized(idx)?);
+    }
+    Ok(compiled)
+  }
+
+  fn compile_function_registerized(&mut self, func_idx: u32) -> Result<Function> {
+    self.func_idx = func_idx;
+    let func = self.module.get_func(func_idx)
+      .ok_or(Error::FuncNotFound)?;
+
+    self.code = func.instructions().to_vec();
+    self.ret_type = func.return_type().map(ValueType::from);
+    self.pc_end = self.code.len();
+
+    let mut rstate = RegCompileState::new();
+    let mut pc = 0;
+    self.compile_block_registerized(&mut rstate, &mut pc, RegBlockKind::Block)?;
+    // The function body's final `End` returns whatever is left on the
+    // operand stack; branches out of the function-level block land here too.
+    let val = if self.ret_type.is_some() { rstate.pop_operand() } else { None };
+    rstate.push(RegInstr::Return { val });
+
+    let program = RegisterProgram {
+      instrs: rstate.instrs,
+      frame_size: rstate.alloc.borrow().frame_size,
+    };
+
+    Ok(Function::new_compiled(func,
+      Box::new(move |state: &vm::State, store: &mut Store| -> Trap<Option<StackValue>> {
+        program.run(state, store)
+      })))
+  }
+
+  fn compile_block_registerized(&self, rstate: &mut RegCompileState, pc: &mut usize, kind: RegBlockKind) -> Result<()> {
+    rstate.blocks.push(RegBlockCtx { kind, loop_start: rstate.instrs.len() as u32, end_patches: vec![] });
+    self.compile_ops_registerized(rstate, pc, kind)?;
+    let ctx = rstate.blocks.pop().expect("block context stack underflow");
+    let end = rstate.instrs.len() as u32;
+    for patch_idx in ctx.end_patches {
+      rstate.instrs[patch_idx].patch_target(end);
+    }
+    Ok(())
+  }
+
+  /// Compiles opcodes into the block context `compile_block_registerized`
+  /// (or `compile_if_registerized`) already pushed, until hitting this
+  /// block's `Else`/`End`. Factored out so an `if`'s two arms can share a
+  /// single `If` context across both calls: a branch out of either arm
+  /// must resolve to the same place — after the whole `if`/`else`, not
+  /// just after whichever arm it was compiled from.
+  fn compile_ops_registerized(&self, rstate: &mut RegCompileState, pc: &mut usize, kind: RegBlockKind) -> Result<()> {
+    loop {
+      use parity_wasm::elements::Instruction::*;
+      if *pc > self.pc_end {
+        break;
+      }
+      let op = &self.code[*pc];
+      match op {
+        Block(_) => {
+          *pc += 1;
+          self.compile_block_registerized(rstate, pc, RegBlockKind::Block)?;
+        },
+        Loop(_) => {
+          *pc += 1;
+          self.compile_block_registerized(rstate, pc, RegBlockKind::Loop)?;
+        },
+        If(_) => {
+          *pc += 1;
+          let cond = rstate.pop_operand().expect("if requires a condition operand");
+          self.compile_if_registerized(rstate, pc, cond)?;
+        },
+        Else => {
+          match kind {
+            RegBlockKind::If => break,
+            _ => return Err(Error::ValidationError(format!("invalid 'else' block, missing 'if'"))),
           }
         },
-        Input::Op(left_closure) => {
-          match right {
-            Input::Local(right_idx) => {
-              state.push(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
-                //eprintln!("-------- fast binop: 1 closures");
-                let left = left_closure(state, store)?;
-                let right = store.stack.get_local_val(right_idx);
-                let res = (left.0 as $type).$op(right.0 as $type);
-                Ok(StackValue(res as _))
-              })));
-              return Ok(());
-            },
-            Input::Const(right_const) => {
-              state.push(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
-                //eprintln!("-------- fast binop: 1 closures");
-                let left = left_closure(state, store)?;
-                let right = right_const;
-                let res = (left.0 as $type).$op(right.0 as $type);
-                Ok(StackValue(res as _))
-              })));
+        End => break,
+        Return => {
+          let val = rstate.pop_operand();
+          rstate.push(RegInstr::Return { val });
+        },
+        Br(relative_depth) => {
+          let target_idx = rstate.blocks.len() - 1 - *relative_depth as usize;
+          rstate.emit_branch(target_idx, None);
+        },
+        BrIf(relative_depth) => {
+          let cond = rstate.pop_operand().expect("BrIf requires a condition operand");
+          let target_idx = rstate.blocks.len() - 1 - *relative_depth as usize;
+          rstate.emit_branch(target_idx, Some(cond));
+        },
+        Call(func_idx) => {
+          let idx = *func_idx;
+          let arg = rstate.pop_operand().expect("Call requires an argument operand");
+          let dst = rstate.alloc_guard();
+          rstate.push(RegInstr::Call { dst: dst.reg(), func_idx: idx, arg });
+          rstate.push_operand(RegValue::Reg(Rc::new(dst)));
+        },
+        GetLocal(local_idx) => {
+          rstate.push_operand(RegValue::Local(*local_idx));
+        },
+        SetLocal(local_idx) => {
+          let idx = *local_idx;
+          let val = rstate.pop_operand().expect("SetLocal requires a value operand");
+          rstate.push(RegInstr::SetLocal { idx, val });
+        },
+        TeeLocal(local_idx) => {
+          let idx = *local_idx;
+          let val = rstate.pop_operand().expect("TeeLocal requires a value operand");
+          rstate.push(RegInstr::SetLocal { idx, val });
+          rstate.push_operand(RegValue::Local(idx));
+        },
+        I32Const(val) => {
+          rstate.push_operand(RegValue::Const(StackValue(*val as _)));
+        },
+        I64Const(val) => {
+          rstate.push_operand(RegValue::Const(StackValue(*val as _)));
+        },
+        I32Add => rstate.binop(IntBinOp::Add),
+        I32Sub => rstate.binop(IntBinOp::Sub),
+        I32LtS => rstate.binop(IntBinOp::LtS),
+        _ => todo!("implment opcode"),
+      };
+      *pc += 1;
+    }
+    Ok(())
+  }
+
+  /// Lowers `if`/`else` to the flat register program: a conditional skip
+  /// over the `if` arm, and (only when an `else` is present) an
+  /// unconditional skip over the `else` arm at the end of the `if` arm.
+  fn compile_if_registerized(&self, rstate: &mut RegCompileState, pc: &mut usize, cond: RegOperand) -> Result<()> {
+    let branch_idx = rstate.instrs.len();
+    rstate.push(RegInstr::BranchIfNot { cond, target: 0 });
+
+    rstate.blocks.push(RegBlockCtx { kind: RegBlockKind::If, loop_start: 0, end_patches: vec![] });
+    self.compile_ops_registerized(rstate, pc, RegBlockKind::If)?;
+
+    use parity_wasm::elements::Instruction::Else;
+    match &self.code[*pc] {
+      Else => {
+        let skip_else_idx = rstate.instrs.len();
+        rstate.push(RegInstr::Branch { target: 0 });
+        let else_start = rstate.instrs.len() as u32;
+        rstate.instrs[branch_idx].patch_target(else_start);
+
+        *pc += 1;
+        self.compile_ops_registerized(rstate, pc, RegBlockKind::If)?;
+
+        let ctx = rstate.blocks.pop().expect("block context stack underflow");
+        let end = rstate.instrs.len() as u32;
+        rstate.instrs[skip_else_idx].patch_target(end);
+        for patch_idx in ctx.end_patches {
+          rstate.instrs[patch_idx].patch_target(end);
+        }
+      },
+      _ => {
+        let ctx = rstate.blocks.pop().expect("block context stack underflow");
+        let end = rstate.instrs.len() as u32;
+        rstate.instrs[branch_idx].patch_target(end);
+        for patch_idx in ctx.end_patches {
+          rstate.instrs[patch_idx].patch_target(end);
+        }
+      },
+    }
+    Ok(())
+  }
+}
+
+/// Index into a function's flat register frame (a `Vec<StackValue>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Reg(u32);
+
+/// Compile-time register allocator: hands out frame slots and recycles
+/// them via [`RegGuard`] as soon as a value's last consumer has been
+/// emitted, instead of growing the frame for every live value.
+struct RegAlloc {
+  free: Vec<u32>,
+  frame_size: u32,
+}
+
+impl RegAlloc {
+  fn new() -> RegAlloc {
+    RegAlloc { free: vec![], frame_size: 0 }
+  }
+
+  fn alloc(&mut self) -> Reg {
+    let idx = self.free.pop().unwrap_or_else(|| {
+      let idx = self.frame_size;
+      self.frame_size += 1;
+      idx
+    });
+    Reg(idx)
+  }
+
+  fn release(&mut self, reg: Reg) {
+    self.free.push(reg.0);
+  }
+}
+
+/// RAII handle over a live register: dropping the last reference to it
+/// returns the slot to the allocator's free list. Shared via `Rc` so a
+/// value that is read by several later operands keeps its register alive
+/// until the last reader resolves it.
+struct RegGuard {
+  alloc: Rc<RefCell<RegAlloc>>,
+  reg: Reg,
+}
+
+impl RegGuard {
+  fn new(alloc: &Rc<RefCell<RegAlloc>>) -> RegGuard {
+    let reg = alloc.borrow_mut().alloc();
+    RegGuard { alloc: Rc::clone(alloc), reg }
+  }
+
+  fn reg(&self) -> Reg {
+    self.reg
+  }
+}
+
+impl Drop for RegGuard {
+  fn drop(&mut self) {
+    self.alloc.borrow_mut().release(self.reg);
+  }
+}
+
+/// A compile-time operand-stack entry for the registerized compiler.
+/// `Const`/`Local` are fused straight into the consuming instruction so
+/// they never occupy a register; `Reg` keeps its backing slot alive for
+/// as long as something still references it.
+#[derive(Clone)]
+enum RegValue {
+  Local(u32),
+  Const(StackValue),
+  Reg(Rc<RegGuard>),
+}
+
+/// A register-or-fused-immediate operand as it appears inside an emitted
+/// [`RegInstr`].
+#[derive(Clone, Copy)]
+enum RegOperand {
+  Local(u32),
+  Const(StackValue),
+  Reg(Reg),
+}
+
+impl RegOperand {
+  fn resolve(&self, frame: &[StackValue], store: &mut Store) -> StackValue {
+    match self {
+      RegOperand::Local(idx) => store.stack.get_local_val(*idx),
+      RegOperand::Const(val) => *val,
+      RegOperand::Reg(reg) => frame[reg.0 as usize],
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum IntBinOp {
+  Add,
+  Sub,
+  LtS,
+}
+
+impl IntBinOp {
+  fn eval(&self, left: StackValue, right: StackValue) -> StackValue {
+    match self {
+      IntBinOp::Add => StackValue(((left.0 as i32).wrapping_add(right.0 as i32)) as _),
+      IntBinOp::Sub => StackValue(((left.0 as i32).wrapping_sub(right.0 as i32)) as _),
+      IntBinOp::LtS => StackValue((((left.0 as i32) < (right.0 as i32)) as i32) as _),
+    }
+  }
+}
+
+enum RegInstr {
+  Binop { dst: Reg, op: IntBinOp, lhs: RegOperand, rhs: RegOperand },
+  Call { dst: Reg, func_idx: u32, arg: RegOperand },
+  SetLocal { idx: u32, val: RegOperand },
+  Return { val: Option<RegOperand> },
+  Branch { target: u32 },
+  BranchIf { cond: RegOperand, target: u32 },
+  BranchIfNot { cond: RegOperand, target: u32 },
+}
+
+impl RegInstr {
+  /// Forward branches are emitted before their target (the end of the
+  /// enclosing block) is known; the block compiler backpatches them once
+  /// it does.
+  fn patch_target(&mut self, new_target: u32) {
+    match self {
+      RegInstr::Branch { target } => *target = new_target,
+      RegInstr::BranchIf { target, .. } => *target = new_target,
+      RegInstr::BranchIfNot { target, .. } => *target = new_target,
+      _ => unreachable!("only branches are backpatched"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RegBlockKind {
+  Block,
+  Loop,
+  If,
+}
+
+/// One entry per currently-open block while compiling. `loop_start` is the
+/// instruction index a `Loop`'s back-edge jumps to; `end_patches` collects
+/// forward branches (out of `Block`/`If`) waiting for this block's end.
+struct RegBlockCtx {
+  kind: RegBlockKind,
+  loop_start: u32,
+  end_patches: Vec<usize>,
+}
+
+struct RegCompileState {
+  instrs: Vec<RegInstr>,
+  operands: Vec<RegValue>,
+  blocks: Vec<RegBlockCtx>,
+  alloc: Rc<RefCell<RegAlloc>>,
+}
+
+impl RegCompileState {
+  fn new() -> RegCompileState {
+    RegCompileState {
+      instrs: vec![],
+      operands: vec![],
+      blocks: vec![],
+      alloc: Rc::new(RefCell::new(RegAlloc::new())),
+    }
+  }
+
+  fn push(&mut self, instr: RegInstr) {
+    self.instrs.push(instr);
+  }
+
+  fn alloc_guard(&self) -> RegGuard {
+    RegGuard::new(&self.alloc)
+  }
+
+  fn push_operand(&mut self, val: RegValue) {
+    self.operands.push(val);
+  }
+
+  fn pop_operand(&mut self) -> Option<RegOperand> {
+    self.operands.pop().map(|val| match val {
+      RegValue::Local(idx) => RegOperand::Local(idx),
+      RegValue::Const(val) => RegOperand::Const(val),
+      RegValue::Reg(guard) => RegOperand::Reg(guard.reg()),
+    })
+  }
+
+  fn binop(&mut self, op: IntBinOp) {
+    let rhs = self.pop_operand().expect("binop requires a right operand");
+    let lhs = self.pop_operand().expect("binop requires a left operand");
+    let guard = self.alloc_guard();
+    let dst = guard.reg();
+    self.push(RegInstr::Binop { dst, op, lhs, rhs });
+    self.push_operand(RegValue::Reg(Rc::new(guard)));
+  }
+
+  /// Emit a branch to the block `target_idx` levels away from the bottom
+  /// of the block stack. A branch into a `Loop` is a back-edge (its target
+  /// is already known); a branch into `Block`/`If` is a forward exit and
+  /// gets queued on that block's `end_patches` for later backpatching.
+  fn emit_branch(&mut self, target_idx: usize, cond: Option<RegOperand>) {
+    let target_kind = self.blocks[target_idx].kind;
+    let placeholder = 0;
+    let instr_idx = self.instrs.len();
+    match cond {
+      Some(cond) => self.push(RegInstr::BranchIf { cond, target: placeholder }),
+      None => self.push(RegInstr::Branch { target: placeholder }),
+    }
+    match target_kind {
+      RegBlockKind::Loop => {
+        let loop_start = self.blocks[target_idx].loop_start;
+        self.instrs[instr_idx].patch_target(loop_start);
+      },
+      _ => {
+        self.blocks[target_idx].end_patches.push(instr_idx);
+      },
+    }
+  }
+}
+
+/// The flat, linear program produced by [`Compiler::compile_registerized`]:
+/// a `Vec<RegInstr>` indexed by program counter and a `frame_size`-deep
+/// register frame allocated once per call, instead of a tree of boxed
+/// closures allocated per operand.
+struct RegisterProgram {
+  instrs: Vec<RegInstr>,
+  frame_size: u32,
+}
+
+impl RegisterProgram {
+  fn run(&self, state: &vm::State, store: &mut Store) -> Trap<Option<StackValue>> {
+    let mut frame = vec![StackValue(0); self.frame_size as usize];
+    let mut pc = 0usize;
+    loop {
+      // Let a REPL/debugger front-end abort a runaway `Loop` (or any
+      // long-running call) back to the prompt instead of hanging.
+      state.check_interrupt()?;
+      match &self.instrs[pc] {
+        RegInstr::Binop { dst, op, lhs, rhs } => {
+          let left = lhs.resolve(&frame, store);
+          let right = rhs.resolve(&frame, store);
+          frame[dst.0 as usize] = op.eval(left, right);
+          pc += 1;
+        },
+        RegInstr::Call { dst, func_idx, arg } => {
+          let val = arg.resolve(&frame, store);
+          store.stack.push_val(val)?;
+          let ret = state.invoke_function(store, *func_idx)?.unwrap_or(StackValue(0));
+          frame[dst.0 as usize] = ret;
+          pc += 1;
+        },
+        RegInstr::SetLocal { idx, val } => {
+          let val = val.resolve(&frame, store);
+          store.stack.set_local_val(*idx, val);
+          pc += 1;
+        },
+        RegInstr::Return { val } => {
+          return Ok(val.map(|operand| operand.resolve(&frame, store)));
+        },
+        RegInstr::Branch { target } => {
+          pc = *target as usize;
+        },
+        RegInstr::BranchIf { cond, target } => {
+          let val = cond.resolve(&frame, store);
+          pc = if val.0 != 0 { *target as usize } else { pc + 1 };
+        },
+        RegInstr::BranchIfNot { cond, target } => {
+          let val = cond.resolve(&frame, store);
+          pc = if val.0 == 0 { *target as usize } else { pc + 1 };
+        },
+      }
+    }
+  }
+}
+
+macro_rules! impl_int_binops {
+  ($name: ident, $type: ty, $op: ident) => {
+    pub fn $name(state: &mut State) -> Result<()> {
+      let (right, right_vn) = state.pop_with_vn()?;
+      let (left, left_vn) = state.pop_with_vn()?;
+
+      // Local value numbering: an identical (opcode, operand) pair seen
+      // earlier in this block already has a cache cell for its result, so
+      // just read that instead of re-emitting/re-running the computation.
+      let vn_key: &'static str = concat!(stringify!($type), "::", stringify!($name));
+      if let Some((cache, class)) = state.cse_lookup(vn_key, left_vn, right_vn) {
+        state.push_with_vn(Input::Op(Box::new(move |_state: &vm::State, _store: &mut Store| -> Trap<StackValue> {
+          Ok(cache.borrow().expect("CSE cache read before its expression ran"))
+        })), class);
+        return Ok(());
+      }
+      let (cache, class) = state.cse_reserve(vn_key, left_vn, right_vn);
+
+      match left {
+        Input::Local(left_idx) => {
+          match right {
+            Input::Const(right_const) => {
+              let cache = Rc::clone(&cache);
+              state.push_with_vn(Input::Op(Box::new(move |_state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+                let left = store.stack.get_local_val(left_idx);
+                let right = right_const;
+                let res = StackValue((left.0 as $type).$op(right.0 as $type) as _);
+                *cache.borrow_mut() = Some(res);
+                Ok(res)
+              })), class);
+              return Ok(());
+            },
+            _ => (),
+          }
+        },
+        Input::Op(left_closure) => {
+          match right {
+            Input::Local(right_idx) => {
+              let cache = Rc::clone(&cache);
+              state.push_with_vn(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+                //eprintln!("-------- fast binop: 1 closures");
+                let left = left_closure(state, store)?;
+                let right = store.stack.get_local_val(right_idx);
+                let res = StackValue((left.0 as $type).$op(right.0 as $type) as _);
+                *cache.borrow_mut() = Some(res);
+                Ok(res)
+              })), class);
+              return Ok(());
+            },
+            Input::Const(right_const) => {
+              let cache = Rc::clone(&cache);
+              state.push_with_vn(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+                //eprintln!("-------- fast binop: 1 closures");
+                let left = left_closure(state, store)?;
+                let right = right_const;
+                let res = StackValue((left.0 as $type).$op(right.0 as $type) as _);
+                *cache.borrow_mut() = Some(res);
+                Ok(res)
+              })), class);
               return Ok(());
             },
             Input::Op(right_closure) => {
-              state.push(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+              let cache = Rc::clone(&cache);
+              state.push_with_vn(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
                 //eprintln!("-------- fast binop: 2 closures");
                 let left = left_closure(state, store)?;
                 let right = right_closure(state, store)?;
-                let res = (left.0 as $type).$op(right.0 as $type);
-                Ok(StackValue(res as _))
-              })));
+                let res = StackValue((left.0 as $type).$op(right.0 as $type) as _);
+                *cache.borrow_mut() = Some(res);
+                Ok(res)
+              })), class);
               return Ok(());
             },
+            // A v128 operand can never reach a scalar binop: the two
+            // value kinds are pushed/popped through disjoint stacks.
+            Input::ConstV128(_) | Input::OpV128(_) => unreachable!("v128 operand in scalar binop"),
           }
         },
         _ => (),
       }
-      state.push(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
+      state.push_with_vn(Input::Op(Box::new(move |state: &vm::State, store: &mut Store| -> Trap<StackValue> {
         eprintln!("-------- slow binop.");
         let left = left.resolv(state, store)?;
         let right = right.resolv(state, store)?;
-        let res = (left.0 as $type).$op(right.0 as $type);
-        Ok(StackValue(res as _))
-      })));
+        let res = StackValue((left.0 as $type).$op(right.0 as $type) as _);
+        *cache.borrow_mut() = Some(res);
+        Ok(res)
+      })), class);
       Ok(())
     }
   };
@@ -585,6 +1791,126 @@ macro_rules! impl_int_relops {
   };
 }
 
+/// Bytes per wasm linear memory page, per the spec.
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// A module instance's linear memory: a flat byte vector sized in whole
+/// pages, grown on demand up to an optional instance-wide page limit.
+pub struct Memory {
+  bytes: Vec<u8>,
+  max_pages: Option<u32>,
+}
+
+impl Memory {
+  pub fn new(initial_pages: u32, max_pages: Option<u32>) -> Self {
+    Memory {
+      bytes: vec![0; initial_pages as usize * PAGE_SIZE],
+      max_pages,
+    }
+  }
+
+  pub fn pages(&self) -> u32 {
+    (self.bytes.len() / PAGE_SIZE) as u32
+  }
+
+  /// Grows the memory by `delta` pages, returning the page count before
+  /// the growth, or `-1` if that would exceed the instance's max-pages
+  /// limit (matching the `memory.grow` instruction's result convention).
+  pub fn grow(&mut self, delta: u32) -> i32 {
+    let prev_pages = self.pages();
+    let new_pages = match prev_pages.checked_add(delta) {
+      Some(new_pages) if self.max_pages.map_or(true, |max| new_pages <= max) => new_pages,
+      _ => return -1,
+    };
+    self.bytes.resize(new_pages as usize * PAGE_SIZE, 0);
+    prev_pages as i32
+  }
+
+  fn bounds_check(&self, addr: u32, width: usize) -> Trap<usize> {
+    let addr = addr as usize;
+    match addr.checked_add(width) {
+      Some(end) if end <= self.bytes.len() => Ok(addr),
+      _ => Err(TrapKind::MemoryAccessOutOfBounds),
+    }
+  }
+
+  pub fn load_bytes(&self, addr: u32, width: usize) -> Trap<&[u8]> {
+    let addr = self.bounds_check(addr, width)?;
+    Ok(&self.bytes[addr..addr + width])
+  }
+
+  pub fn store_bytes(&mut self, addr: u32, data: &[u8]) -> Trap<()> {
+    let addr = self.bounds_check(addr, data.len())?;
+    self.bytes[addr..addr + data.len()].copy_from_slice(data);
+    Ok(())
+  }
+}
+
+/// The effective address of a `load`/`store`: the dynamic base operand
+/// plus the instruction's static `offset` immediate, per the wasm spec.
+/// Overflowing this addition can never land inside any valid memory, so
+/// it traps the same as an out-of-bounds access rather than wrapping.
+fn effective_addr(base: u32, offset: u32) -> Trap<u32> {
+  base.checked_add(offset).ok_or(TrapKind::MemoryAccessOutOfBounds)
+}
+
+#[cfg(test)]
+mod memory_tests {
+  use super::*;
+
+  #[test]
+  fn exact_boundary_access_succeeds() {
+    let mem = Memory::new(1, None);
+    assert!(mem.load_bytes((PAGE_SIZE - 4) as u32, 4).is_ok());
+  }
+
+  #[test]
+  fn one_past_boundary_access_traps() {
+    let mem = Memory::new(1, None);
+    assert!(mem.load_bytes((PAGE_SIZE - 3) as u32, 4).is_err());
+  }
+
+  #[test]
+  fn store_bytes_respects_the_same_boundary() {
+    let mut mem = Memory::new(1, None);
+    assert!(mem.store_bytes((PAGE_SIZE - 4) as u32, &[1, 2, 3, 4]).is_ok());
+    assert!(mem.store_bytes((PAGE_SIZE - 3) as u32, &[1, 2, 3, 4]).is_err());
+  }
+
+  #[test]
+  fn addr_plus_width_overflow_traps_instead_of_wrapping() {
+    let mem = Memory::new(1, None);
+    assert!(mem.load_bytes(u32::MAX, 4).is_err());
+  }
+
+  #[test]
+  fn effective_addr_overflow_traps() {
+    assert!(effective_addr(u32::MAX, 1).is_err());
+    assert!(effective_addr(u32::MAX - 1, 1).is_ok());
+  }
+
+  #[test]
+  fn grow_succeeds_up_to_max_pages() {
+    let mut mem = Memory::new(1, Some(2));
+    assert_eq!(mem.grow(1), 1);
+    assert_eq!(mem.pages(), 2);
+  }
+
+  #[test]
+  fn grow_past_max_pages_fails_without_growing() {
+    let mut mem = Memory::new(1, Some(2));
+    assert_eq!(mem.grow(2), -1);
+    assert_eq!(mem.pages(), 1);
+  }
+
+  #[test]
+  fn grow_exactly_to_max_pages_succeeds() {
+    let mut mem = Memory::new(0, Some(1));
+    assert_eq!(mem.grow(1), 0);
+    assert_eq!(mem.pages(), 1);
+  }
+}
+
 macro_rules! impl_numeric_ops {
   ($op_mod: ident, $type: ty, $type_u: ty) => {
     #[allow(dead_code)]
@@ -592,39 +1918,76 @@ macro_rules! impl_numeric_ops {
       use std::ops::*;
       use super::*;
 
-      pub fn load(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let bytes = store.memory.load_bytes(addr, std::mem::size_of::<$type>())?;
+        store.stack.push(<$type>::from_le_bytes(bytes.try_into().unwrap()))
       }
-      pub fn load8_s(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load8_s(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let byte = store.memory.load_bytes(addr, 1)?[0];
+        store.stack.push(byte as i8 as $type)
       }
-      pub fn load8_u(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load8_u(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let byte = store.memory.load_bytes(addr, 1)?[0];
+        store.stack.push(byte as $type_u as $type)
       }
-      pub fn load16_s(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load16_s(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let bytes = store.memory.load_bytes(addr, 2)?;
+        let val = i16::from_le_bytes(bytes.try_into().unwrap());
+        store.stack.push(val as $type)
       }
-      pub fn load16_u(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load16_u(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let bytes = store.memory.load_bytes(addr, 2)?;
+        let val = u16::from_le_bytes(bytes.try_into().unwrap());
+        store.stack.push(val as $type_u as $type)
       }
-      pub fn load32_s(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load32_s(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let bytes = store.memory.load_bytes(addr, 4)?;
+        let val = i32::from_le_bytes(bytes.try_into().unwrap());
+        store.stack.push(val as $type)
       }
-      pub fn load32_u(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load32_u(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let bytes = store.memory.load_bytes(addr, 4)?;
+        let val = u32::from_le_bytes(bytes.try_into().unwrap());
+        store.stack.push(val as $type_u as $type)
       }
 
-      pub fn store(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn store(store: &mut Store, offset: u32) -> Trap<()> {
+        let val: $type = store.stack.pop()?;
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        store.memory.store_bytes(addr, &val.to_le_bytes())
       }
-      pub fn store8(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn store8(store: &mut Store, offset: u32) -> Trap<()> {
+        let val: $type = store.stack.pop()?;
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        store.memory.store_bytes(addr, &[(val as $type_u) as u8])
       }
-      pub fn store16(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn store16(store: &mut Store, offset: u32) -> Trap<()> {
+        let val: $type = store.stack.pop()?;
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        store.memory.store_bytes(addr, &((val as $type_u) as u16).to_le_bytes())
       }
-      pub fn store32(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn store32(store: &mut Store, offset: u32) -> Trap<()> {
+        let val: $type = store.stack.pop()?;
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        store.memory.store_bytes(addr, &((val as $type_u) as u32).to_le_bytes())
       }
 
       pub fn clz(store: &mut Store) -> Trap<()> {
@@ -720,12 +2083,18 @@ macro_rules! impl_float_numeric_ops {
 
       use super::*;
 
-      pub fn load(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn load(store: &mut Store, offset: u32) -> Trap<()> {
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        let bytes = store.memory.load_bytes(addr, std::mem::size_of::<$type>())?;
+        store.stack.push(<$type>::from_le_bytes(bytes.try_into().unwrap()))
       }
 
-      pub fn store(_store: &mut Store, _offset: u32) -> Trap<()> {
-        todo!();
+      pub fn store(store: &mut Store, offset: u32) -> Trap<()> {
+        let val: $type = store.stack.pop()?;
+        let base: u32 = store.stack.pop()?;
+        let addr = effective_addr(base, offset)?;
+        store.memory.store_bytes(addr, &val.to_le_bytes())
       }
 
       pub fn abs(_store: &mut Store) -> Trap<()> {
@@ -820,3 +2189,302 @@ macro_rules! impl_float_numeric_ops {
 impl_float_numeric_ops!(f32_ops, f32);
 impl_float_numeric_ops!(f64_ops, f64);
 
+/// 128-bit SIMD value: sixteen raw bytes, reinterpreted per [`LaneType`]
+/// by the lanewise ops in `v128_ops` below. Kept separate from the
+/// scalar `StackValue` rather than widening it (see `Input::ConstV128`).
+#[derive(Debug, Clone, Copy)]
+pub struct V128(pub [u8; 16]);
+
+/// Mirrors the SIMD proposal's scalar/vector type split: a `v128` value
+/// carries no type of its own, so every op is parameterized by how its
+/// operands should be sliced into lanes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneType {
+  I8x16,
+  I16x8,
+  I32x4,
+  I64x2,
+  F32x4,
+  F64x2,
+}
+
+impl LaneType {
+  fn lane_count(&self) -> usize {
+    match self {
+      LaneType::I8x16 => 16,
+      LaneType::I16x8 => 8,
+      LaneType::I32x4 => 4,
+      LaneType::I64x2 => 2,
+      LaneType::F32x4 => 4,
+      LaneType::F64x2 => 2,
+    }
+  }
+}
+
+/// `extract_lane`/`replace_lane`'s lane index is a compile-time constant
+/// in the wasm encoding (not a value popped off the stack), so it's
+/// checked here while compiling the opcode; the re-check that
+/// `extract_lane`/`replace_lane` make on every invocation of the emitted
+/// closure can then never fail.
+fn validate_lane_index(lane_type: LaneType, lane: u32) -> Result<()> {
+  if (lane as usize) < lane_type.lane_count() {
+    Ok(())
+  } else {
+    Err(Error::ValidationError(format!(
+      "lane index {} out of bounds for {:?} ({} lanes)", lane, lane_type, lane_type.lane_count()
+    )))
+  }
+}
+
+/// Lets `impl_v128_lane_ops!` share one `add`/`sub`/`mul` body across
+/// integer lanes (wrapping, like the scalar `wrapping_add` family) and
+/// float lanes (plain IEEE arithmetic).
+trait LaneArith: Copy {
+  fn lane_add(self, other: Self) -> Self;
+  fn lane_sub(self, other: Self) -> Self;
+  fn lane_mul(self, other: Self) -> Self;
+}
+
+macro_rules! impl_lane_arith_int {
+  ($t: ty) => {
+    impl LaneArith for $t {
+      fn lane_add(self, other: Self) -> Self { self.wrapping_add(other) }
+      fn lane_sub(self, other: Self) -> Self { self.wrapping_sub(other) }
+      fn lane_mul(self, other: Self) -> Self { self.wrapping_mul(other) }
+    }
+  };
+}
+
+macro_rules! impl_lane_arith_float {
+  ($t: ty) => {
+    impl LaneArith for $t {
+      fn lane_add(self, other: Self) -> Self { self + other }
+      fn lane_sub(self, other: Self) -> Self { self - other }
+      fn lane_mul(self, other: Self) -> Self { self * other }
+    }
+  };
+}
+
+impl_lane_arith_int!(i8);
+impl_lane_arith_int!(i16);
+impl_lane_arith_int!(i32);
+impl_lane_arith_int!(i64);
+impl_lane_arith_float!(f32);
+impl_lane_arith_float!(f64);
+
+/// Bitwise ops operate on the raw bytes and don't care about lane shape,
+/// so they live outside the per-`LaneType` macro below.
+#[allow(dead_code)]
+mod v128_bitops {
+  use super::*;
+
+  pub fn and(left: V128, right: V128) -> V128 {
+    let mut out = [0u8; 16];
+    for i in 0..16 {
+      out[i] = left.0[i] & right.0[i];
+    }
+    V128(out)
+  }
+
+  pub fn or(left: V128, right: V128) -> V128 {
+    let mut out = [0u8; 16];
+    for i in 0..16 {
+      out[i] = left.0[i] | right.0[i];
+    }
+    V128(out)
+  }
+
+  pub fn xor(left: V128, right: V128) -> V128 {
+    let mut out = [0u8; 16];
+    for i in 0..16 {
+      out[i] = left.0[i] ^ right.0[i];
+    }
+    V128(out)
+  }
+
+  pub fn not(val: V128) -> V128 {
+    let mut out = [0u8; 16];
+    for i in 0..16 {
+      out[i] = !val.0[i];
+    }
+    V128(out)
+  }
+}
+
+macro_rules! impl_v128_lane_ops {
+  ($op_mod: ident, $lane_type: expr, $elem: ty, $lanes: expr) => {
+    #[allow(dead_code)]
+    mod $op_mod {
+      use super::*;
+
+      fn lane_size() -> usize {
+        std::mem::size_of::<$elem>()
+      }
+
+      fn read_lane(v: &V128, lane: usize) -> $elem {
+        let size = lane_size();
+        let mut bytes = [0u8; std::mem::size_of::<$elem>()];
+        bytes.copy_from_slice(&v.0[lane * size..(lane + 1) * size]);
+        <$elem>::from_le_bytes(bytes)
+      }
+
+      fn write_lane(v: &mut V128, lane: usize, val: $elem) {
+        let size = lane_size();
+        v.0[lane * size..(lane + 1) * size].copy_from_slice(&val.to_le_bytes());
+      }
+
+      /// Every lane immediate (`extract_lane`/`replace_lane`'s index) is a
+      /// compile-time constant in the wasm encoding, so an out-of-range
+      /// lane is a validation error, not a runtime trap.
+      fn validate_lane(lane: u32) -> Result<()> {
+        if (lane as usize) < $lanes {
+          Ok(())
+        } else {
+          Err(Error::ValidationError(format!(
+            "lane index {} out of bounds for {:?} ({} lanes)", lane, $lane_type, $lanes
+          )))
+        }
+      }
+
+      pub fn splat(val: $elem) -> V128 {
+        let mut v = V128([0u8; 16]);
+        for lane in 0..$lanes {
+          write_lane(&mut v, lane, val);
+        }
+        v
+      }
+
+      pub fn extract_lane(v: &V128, lane: u32) -> Result<$elem> {
+        validate_lane(lane)?;
+        Ok(read_lane(v, lane as usize))
+      }
+
+      pub fn replace_lane(v: &V128, lane: u32, val: $elem) -> Result<V128> {
+        validate_lane(lane)?;
+        let mut out = *v;
+        write_lane(&mut out, lane as usize, val);
+        Ok(out)
+      }
+
+      fn lanewise(left: &V128, right: &V128, op: impl Fn($elem, $elem) -> $elem) -> V128 {
+        let mut out = V128([0u8; 16]);
+        for lane in 0..$lanes {
+          let res = op(read_lane(left, lane), read_lane(right, lane));
+          write_lane(&mut out, lane, res);
+        }
+        out
+      }
+
+      pub fn add(left: &V128, right: &V128) -> V128 {
+        lanewise(left, right, |l: $elem, r: $elem| l.lane_add(r))
+      }
+
+      pub fn sub(left: &V128, right: &V128) -> V128 {
+        lanewise(left, right, |l: $elem, r: $elem| l.lane_sub(r))
+      }
+
+      pub fn mul(left: &V128, right: &V128) -> V128 {
+        lanewise(left, right, |l: $elem, r: $elem| l.lane_mul(r))
+      }
+
+      pub fn min(left: &V128, right: &V128) -> V128 {
+        lanewise(left, right, |l, r| if l < r { l } else { r })
+      }
+
+      pub fn max(left: &V128, right: &V128) -> V128 {
+        lanewise(left, right, |l, r| if l > r { l } else { r })
+      }
+    }
+  };
+}
+
+impl_v128_lane_ops!(i8x16_ops, LaneType::I8x16, i8, 16);
+impl_v128_lane_ops!(i16x8_ops, LaneType::I16x8, i16, 8);
+impl_v128_lane_ops!(i32x4_ops, LaneType::I32x4, i32, 4);
+impl_v128_lane_ops!(i64x2_ops, LaneType::I64x2, i64, 2);
+impl_v128_lane_ops!(f32x4_ops, LaneType::F32x4, f32, 4);
+impl_v128_lane_ops!(f64x2_ops, LaneType::F64x2, f64, 2);
+
+#[cfg(test)]
+mod v128_tests {
+  use super::*;
+
+  #[test]
+  fn validate_lane_index_accepts_in_range_and_rejects_out_of_range() {
+    assert!(validate_lane_index(LaneType::I8x16, 15).is_ok());
+    assert!(validate_lane_index(LaneType::I8x16, 16).is_err());
+    assert!(validate_lane_index(LaneType::F64x2, 1).is_ok());
+    assert!(validate_lane_index(LaneType::F64x2, 2).is_err());
+  }
+
+  #[test]
+  fn splat_fills_every_lane_with_the_same_value() {
+    let v = i32x4_ops::splat(-7);
+    for lane in 0..4 {
+      assert_eq!(i32x4_ops::extract_lane(&v, lane).unwrap(), -7);
+    }
+
+    let v = f32x4_ops::splat(1.5);
+    for lane in 0..4 {
+      assert_eq!(f32x4_ops::extract_lane(&v, lane).unwrap(), 1.5);
+    }
+  }
+
+  #[test]
+  fn replace_lane_overwrites_only_the_given_lane() {
+    let v = i16x8_ops::splat(0);
+    let v = i16x8_ops::replace_lane(&v, 3, 42).unwrap();
+    for lane in 0..8 {
+      let expected = if lane == 3 { 42 } else { 0 };
+      assert_eq!(i16x8_ops::extract_lane(&v, lane).unwrap(), expected);
+    }
+  }
+
+  #[test]
+  fn extract_lane_and_replace_lane_reject_out_of_bounds_indices() {
+    let v = i64x2_ops::splat(0);
+    assert!(i64x2_ops::extract_lane(&v, 2).is_err());
+    assert!(i64x2_ops::replace_lane(&v, 2, 1).is_err());
+  }
+
+  #[test]
+  fn int_lane_arithmetic_wraps_instead_of_panicking() {
+    let left = i8x16_ops::splat(i8::MAX);
+    let right = i8x16_ops::splat(1);
+    let sum = i8x16_ops::add(&left, &right);
+    assert_eq!(i8x16_ops::extract_lane(&sum, 0).unwrap(), i8::MIN);
+
+    let diff = i8x16_ops::sub(&i8x16_ops::splat(i8::MIN), &right);
+    assert_eq!(i8x16_ops::extract_lane(&diff, 0).unwrap(), i8::MAX);
+  }
+
+  #[test]
+  fn float_lane_arithmetic_is_plain_ieee_add_sub_mul() {
+    let left = f64x2_ops::splat(1.5);
+    let right = f64x2_ops::splat(2.25);
+    assert_eq!(f64x2_ops::extract_lane(&f64x2_ops::add(&left, &right), 0).unwrap(), 3.75);
+    assert_eq!(f64x2_ops::extract_lane(&f64x2_ops::sub(&left, &right), 0).unwrap(), -0.75);
+    assert_eq!(f64x2_ops::extract_lane(&f64x2_ops::mul(&left, &right), 0).unwrap(), 3.375);
+  }
+
+  #[test]
+  fn lanewise_min_and_max_pick_per_lane() {
+    let left = i32x4_ops::replace_lane(&i32x4_ops::splat(0), 1, 5).unwrap();
+    let right = i32x4_ops::replace_lane(&i32x4_ops::splat(0), 1, -3).unwrap();
+    let min = i32x4_ops::min(&left, &right);
+    let max = i32x4_ops::max(&left, &right);
+    assert_eq!(i32x4_ops::extract_lane(&min, 1).unwrap(), -3);
+    assert_eq!(i32x4_ops::extract_lane(&max, 1).unwrap(), 5);
+  }
+
+  #[test]
+  fn bitwise_ops_operate_on_raw_bytes() {
+    let all_ones = V128([0xff; 16]);
+    let all_zeros = V128([0x00; 16]);
+    assert_eq!(v128_bitops::and(all_ones, all_zeros).0, [0x00; 16]);
+    assert_eq!(v128_bitops::or(all_ones, all_zeros).0, [0xff; 16]);
+    assert_eq!(v128_bitops::xor(all_ones, all_ones).0, [0x00; 16]);
+    assert_eq!(v128_bitops::not(all_zeros).0, [0xff; 16]);
+  }
+}
+
@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// Errors raised while compiling a module: malformed/unsupported wasm, or an
+/// internal invariant the compiler can't proceed past.
+#[derive(Debug, Clone)]
+pub enum Error {
+  ValidationError(String),
+  FuncNotFound,
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Error::ValidationError(msg) => write!(f, "{}", msg),
+      Error::FuncNotFound => write!(f, "function not found"),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors a compiled function can raise while running (the wasm spec's
+/// "trap"), as opposed to [`Error`], which only ever happens at compile time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapKind {
+  DivisionByZero,
+  InvalidConversionToInt,
+  MemoryAccessOutOfBounds,
+  Interrupted,
+}
+
+impl fmt::Display for TrapKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TrapKind::DivisionByZero => write!(f, "integer divide by zero"),
+      TrapKind::InvalidConversionToInt => write!(f, "invalid conversion to integer"),
+      TrapKind::MemoryAccessOutOfBounds => write!(f, "out of bounds memory access"),
+      TrapKind::Interrupted => write!(f, "interrupted"),
+    }
+  }
+}
+
+impl std::error::Error for TrapKind {}
+
+pub type Trap<T> = std::result::Result<T, TrapKind>;
@@ -0,0 +1,48 @@
+use std::rc::Rc;
+
+use crate::error::*;
+use crate::{vm, Store, StackValue};
+
+/// A single compiled export: its call signature (taken from the `bwasm`
+/// function it was compiled from) plus the compiled body, which may be
+/// either backend in [`crate::compiler::Compiler`] (closure-tree or
+/// registerized) — both produce the same `Fn(&vm::State, &mut Store) ->
+/// Trap<Option<StackValue>>` shape, so `Function` doesn't need to know
+/// which one it's holding.
+#[derive(Clone)]
+pub struct Function {
+  name: String,
+  num_params: u32,
+  num_locals: u32,
+  body: Rc<dyn Fn(&vm::State, &mut Store) -> Trap<Option<StackValue>>>,
+}
+
+impl Function {
+  pub fn new_compiled(
+    func: &bwasm::Function,
+    body: Box<dyn Fn(&vm::State, &mut Store) -> Trap<Option<StackValue>>>,
+  ) -> Function {
+    Function {
+      name: func.name().to_string(),
+      num_params: func.param_count(),
+      num_locals: func.param_count() + func.locals().len() as u32,
+      body: Rc::from(body),
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn num_params(&self) -> u32 {
+    self.num_params
+  }
+
+  pub fn num_locals(&self) -> u32 {
+    self.num_locals
+  }
+
+  pub fn call(&self, state: &vm::State, store: &mut Store) -> Trap<Option<StackValue>> {
+    (self.body)(state, store)
+  }
+}
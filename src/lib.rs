@@ -0,0 +1,186 @@
+pub mod compiler;
+pub mod error;
+pub mod function;
+pub mod vm;
+
+// Re-exported so downstream crates (the `repl` binary, the benchmarks) can
+// build/inspect modules through `s1vm::bwasm` without taking their own
+// dependency on it directly.
+pub use bwasm;
+
+pub use error::*;
+
+/// A single wasm value, stored as its raw bits rather than a tagged union:
+/// every opcode already knows the type it's working with (from the module's
+/// validated signatures), so there's nothing to gain from tagging the value
+/// itself — only a conversion at each read/write site via
+/// [`FromStackValue`]/[`IntoStackValue`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StackValue(pub i64);
+
+pub trait FromStackValue {
+  fn from_stack_value(val: StackValue) -> Self;
+}
+
+pub trait IntoStackValue {
+  fn into_stack_value(self) -> StackValue;
+}
+
+macro_rules! impl_stack_value_conv_int {
+  ($($t:ty),*) => {
+    $(
+      impl FromStackValue for $t {
+        fn from_stack_value(val: StackValue) -> Self {
+          val.0 as $t
+        }
+      }
+      impl IntoStackValue for $t {
+        fn into_stack_value(self) -> StackValue {
+          StackValue(self as i64)
+        }
+      }
+    )*
+  };
+}
+impl_stack_value_conv_int!(i8, u8, i16, u16, i32, u32, i64, u64);
+
+impl FromStackValue for f32 {
+  fn from_stack_value(val: StackValue) -> Self {
+    f32::from_bits(val.0 as u32)
+  }
+}
+impl IntoStackValue for f32 {
+  fn into_stack_value(self) -> StackValue {
+    StackValue(self.to_bits() as i64)
+  }
+}
+
+impl FromStackValue for f64 {
+  fn from_stack_value(val: StackValue) -> Self {
+    f64::from_bits(val.0 as u64)
+  }
+}
+impl IntoStackValue for f64 {
+  fn into_stack_value(self) -> StackValue {
+    StackValue(self.to_bits() as i64)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+  I32,
+  I64,
+  F32,
+  F64,
+}
+
+impl From<bwasm::ValueType> for ValueType {
+  fn from(value_type: bwasm::ValueType) -> Self {
+    match value_type {
+      bwasm::ValueType::I32 => ValueType::I32,
+      bwasm::ValueType::I64 => ValueType::I64,
+      bwasm::ValueType::F32 => ValueType::F32,
+      bwasm::ValueType::F64 => ValueType::F64,
+    }
+  }
+}
+
+/// A single call frame's locals (params followed by declared locals).
+struct Frame {
+  locals: Vec<StackValue>,
+}
+
+/// The operand stack a compiled function's opcodes push/pop through, plus
+/// the call frames backing `local.get`/`local.set`/`local.tee`. Kept as one
+/// type (rather than splitting locals out into their own structure) because
+/// a `Call` opcode threads a value through both: it's popped off the
+/// operand stack as the callee's argument, then lives on as that callee's
+/// local 0.
+pub struct Stack {
+  values: Vec<StackValue>,
+  frames: Vec<Frame>,
+}
+
+impl Stack {
+  fn new() -> Stack {
+    Stack { values: vec![], frames: vec![] }
+  }
+
+  pub fn get_local_val(&self, idx: u32) -> StackValue {
+    self.frames.last().expect("get_local_val outside of a call frame")
+      .locals[idx as usize]
+  }
+
+  pub fn set_local_val(&mut self, idx: u32, val: StackValue) {
+    self.frames.last_mut().expect("set_local_val outside of a call frame")
+      .locals[idx as usize] = val;
+  }
+
+  pub fn push_val(&mut self, val: StackValue) -> Trap<()> {
+    self.values.push(val);
+    Ok(())
+  }
+
+  fn pop_val(&mut self) -> StackValue {
+    self.values.pop().expect("operand stack underflow")
+  }
+
+  pub(crate) fn push_frame(&mut self, locals: Vec<StackValue>) {
+    self.frames.push(Frame { locals });
+  }
+
+  pub(crate) fn pop_frame(&mut self) {
+    self.frames.pop().expect("frame stack underflow");
+  }
+
+  pub(crate) fn pop_frame_arg(&mut self) -> StackValue {
+    self.pop_val()
+  }
+
+  pub fn pop<T: FromStackValue>(&mut self) -> Trap<T> {
+    Ok(T::from_stack_value(self.pop_val()))
+  }
+
+  pub fn push<T: IntoStackValue>(&mut self, val: T) -> Trap<()> {
+    self.values.push(val.into_stack_value());
+    Ok(())
+  }
+
+  pub fn pop_pair<T: FromStackValue, U: FromStackValue>(&mut self) -> Trap<(T, U)> {
+    let right = self.pop_val();
+    let left = self.pop_val();
+    Ok((T::from_stack_value(left), U::from_stack_value(right)))
+  }
+
+  /// Pops the two operands of a binary op, lets `f` compute the result in
+  /// place of `left`, and pushes that back. Shared by the ops (like integer
+  /// division) that need to trap instead of unconditionally producing a
+  /// value.
+  pub fn binop<F: FnOnce(&mut StackValue, &mut StackValue) -> Trap<()>>(&mut self, f: F) -> Trap<()> {
+    let mut right = self.pop_val();
+    let mut left = self.pop_val();
+    f(&mut left, &mut right)?;
+    self.values.push(left);
+    Ok(())
+  }
+}
+
+/// A module instance's mutable state: its operand/local stack and its
+/// linear memory. Built once per loaded module and threaded through every
+/// call via `&mut Store`.
+pub struct Store {
+  pub stack: Stack,
+  pub memory: compiler::Memory,
+}
+
+impl Store {
+  pub fn new(module: &bwasm::Module) -> Store {
+    let (initial_pages, max_pages) = module.memories().first()
+      .map(|mem| (mem.limits().initial(), mem.limits().maximum()))
+      .unwrap_or((0, None));
+    Store {
+      stack: Stack::new(),
+      memory: compiler::Memory::new(initial_pages, max_pages),
+    }
+  }
+}
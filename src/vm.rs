@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::compiler::Action;
+use crate::error::*;
+use crate::function::Function;
+use crate::{Store, StackValue};
+
+/// Execution context threaded (by shared reference) through every compiled
+/// closure and register program for the lifetime of a single top-level
+/// [`State::call`]. Carries the interrupt flag a REPL/debugger front-end can
+/// flip from another thread, and — when single-stepping — the hook that
+/// pauses execution and reports back after each evaluated instruction.
+pub struct State {
+  interrupted: Arc<AtomicBool>,
+  single_step: bool,
+  // Stashed by `call` for the duration of that call so nested `Call`
+  // opcodes (which only have `state`/`store` in scope, not the compiled
+  // function table) can still resolve a callee by index via
+  // `invoke_function`. Cloning a `Function` only clones its `Rc`'d body and
+  // a little metadata, so this is cheap.
+  functions: RefCell<Vec<Function>>,
+}
+
+impl State {
+  pub fn new() -> State {
+    State::new_interruptible(Arc::new(AtomicBool::new(false)), false)
+  }
+
+  pub fn new_interruptible(interrupted: Arc<AtomicBool>, single_step: bool) -> State {
+    State {
+      interrupted,
+      single_step,
+      functions: RefCell::new(vec![]),
+    }
+  }
+
+  /// Entry point for invoking a compiled export from outside the VM (a REPL,
+  /// a benchmark): stages `args` as the callee's parameters and runs it.
+  pub fn call(
+    &self,
+    store: &mut Store,
+    compiled: &[Function],
+    func_idx: u32,
+    args: &[i64],
+  ) -> Trap<Option<StackValue>> {
+    *self.functions.borrow_mut() = compiled.to_vec();
+    for &arg in args {
+      store.stack.push_val(StackValue(arg))?;
+    }
+    self.invoke_function(store, func_idx)
+  }
+
+  /// Invokes a callee by index from inside a compiled body (the `Call`
+  /// opcode): pops its arguments off the operand stack into a fresh local
+  /// frame, runs its compiled body, and tears the frame down again.
+  pub fn invoke_function(&self, store: &mut Store, func_idx: u32) -> Trap<Option<StackValue>> {
+    let func = self.functions.borrow()[func_idx as usize].clone();
+    let mut locals = vec![StackValue(0); func.num_locals() as usize];
+    for idx in (0..func.num_params()).rev() {
+      locals[idx as usize] = store.stack.pop_frame_arg();
+    }
+    store.stack.push_frame(locals);
+    let ret = func.call(self, store);
+    store.stack.pop_frame();
+    ret
+  }
+
+  /// Lets a REPL/debugger front-end abort a runaway `Loop` (or any
+  /// long-running call) back to the prompt instead of hanging.
+  pub fn check_interrupt(&self) -> Trap<()> {
+    if self.interrupted.swap(false, Ordering::SeqCst) {
+      Err(TrapKind::Interrupted)
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Called by the closure-tree backend's `Block::run` once per evaluated
+  /// instruction (the registerized backend's `RegisterProgram::run` does not
+  /// call it, so it does not single-step); in single-step mode, reports the
+  /// action that just ran and blocks until the front-end confirms it should
+  /// continue, so the REPL's `step` command has something to actually pause.
+  pub fn on_step(&self, action: &Action) -> Trap<()> {
+    if self.single_step {
+      println!("step: {:?}", action);
+      let mut line = String::new();
+      let _ = std::io::stdin().read_line(&mut line);
+    }
+    Ok(())
+  }
+}